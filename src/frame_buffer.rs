@@ -5,26 +5,123 @@ use header::StompHeaderSet;
 use header::HeaderCodec;
 use std::str::from_utf8;
 use std::mem;
+use std::fmt;
+use std::error::Error;
 use frame::{Frame, Transmission};
 use lifeguard::Pool;
-use std::collections::VecDeque;
+use bytes::{Bytes, BytesMut};
+use memchr::memchr;
 
 const DEFAULT_STRING_POOL_SIZE: usize = 4;
 const DEFAULT_STRING_POOL_MAX_SIZE: usize = 32;
 const DEFAULT_HEADER_CODEC_STRING_POOL_SIZE: usize = 16;
 const DEFAULT_HEADER_CODEC_STRING_POOL_MAX_SIZE: usize = 64;
 
+// Generous defaults; callers talking to untrusted peers should tighten these.
+const DEFAULT_MAX_HEADER_LINE_LENGTH: usize = 8 * 1024;
+const DEFAULT_MAX_HEADER_COUNT: usize = 1000;
+const DEFAULT_MAX_BODY_LENGTH: usize = 16 * 1024 * 1024;
+// A frame is COMMAND + headers + body + a trailing null octet, so the frame
+// cap must clear the body cap by more than just that one byte or an
+// at-limit body becomes unrepresentable. Leave room for a full house of
+// max-length headers plus the command line, the blank line that ends the
+// headers and the null octet.
+const DEFAULT_MAX_FRAME_SIZE: usize =
+  DEFAULT_MAX_BODY_LENGTH + (DEFAULT_MAX_HEADER_COUNT * DEFAULT_MAX_HEADER_LINE_LENGTH) + DEFAULT_MAX_HEADER_LINE_LENGTH + 2;
+
+/// Limits used to bound how much memory a single frame is allowed to consume
+/// while it is being parsed out of the wire.
+pub struct FrameBufferConfig {
+  pub max_frame_size: usize,
+  pub max_header_line_length: usize,
+  pub max_header_count: usize,
+  pub max_body_length: usize
+}
+
+impl FrameBufferConfig {
+  pub fn new(max_frame_size: usize,
+             max_header_line_length: usize,
+             max_header_count: usize,
+             max_body_length: usize) -> FrameBufferConfig {
+    FrameBufferConfig {
+      max_frame_size: max_frame_size,
+      max_header_line_length: max_header_line_length,
+      max_header_count: max_header_count,
+      max_body_length: max_body_length
+    }
+  }
+}
+
+impl Default for FrameBufferConfig {
+  fn default() -> FrameBufferConfig {
+    FrameBufferConfig {
+      max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+      max_header_line_length: DEFAULT_MAX_HEADER_LINE_LENGTH,
+      max_header_count: DEFAULT_MAX_HEADER_COUNT,
+      max_body_length: DEFAULT_MAX_BODY_LENGTH
+    }
+  }
+}
+
 pub struct FrameBuffer {
-  buffer: VecDeque<u8>,
+  buffer: BytesMut,
   parse_state: ParseState,
   string_pool: Pool<String>,
-  header_codec: HeaderCodec
+  header_codec: HeaderCodec,
+  config: FrameBufferConfig,
+  consumed_bytes: u64
+}
+
+/// A recoverable parse failure, tagged with the absolute byte offset (since
+/// this `FrameBuffer` was created) at which it was detected. Callers can use
+/// the offset to log where a misbehaving peer's stream went bad and decide
+/// whether to drop just the in-flight frame or reset the whole connection.
+#[derive(Debug)]
+pub enum ParseError {
+  InvalidUtf8 { offset: u64 },
+  MalformedHeader { offset: u64 },
+  LimitExceeded { offset: u64, limit: &'static str },
+  UnexpectedState { offset: u64, message: &'static str },
+  Io { offset: u64, message: String }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ParseError::InvalidUtf8 { offset } =>
+        write!(f, "Invalid UTF-8 encountered at byte offset {}.", offset),
+      ParseError::MalformedHeader { offset } =>
+        write!(f, "Malformed header encountered at byte offset {}.", offset),
+      ParseError::LimitExceeded { offset, limit } =>
+        write!(f, "Exceeded the configured {} limit at byte offset {}.", limit, offset),
+      ParseError::UnexpectedState { offset, message } =>
+        write!(f, "Unexpected parser state at byte offset {}: {}.", offset, message),
+      ParseError::Io { offset, ref message } =>
+        write!(f, "I/O error at byte offset {}: {}.", offset, message)
+    }
+  }
+}
+
+impl Error for ParseError {
+  fn description(&self) -> &str {
+    match *self {
+      ParseError::InvalidUtf8 { .. } => "invalid utf-8",
+      ParseError::MalformedHeader { .. } => "malformed header",
+      ParseError::LimitExceeded { .. } => "limit exceeded",
+      ParseError::UnexpectedState { .. } => "unexpected parser state",
+      ParseError::Io { .. } => "i/o error"
+    }
+  }
 }
 
 struct ParseState {
   command: Option<String>,
   headers: HeaderList,
   section: FrameSection,
+  // How far into the buffer we've already scanned looking for the next
+  // delimiter. Lets read_command/read_header resume scanning where they left
+  // off instead of re-walking bytes a prior, incomplete call already saw.
+  scan_offset: usize,
 }
 
 impl ParseState {
@@ -32,7 +129,8 @@ impl ParseState {
     ParseState {
       command: None,
       headers: header_list![],
-      section: FrameSection::Command
+      section: FrameSection::Command,
+      scan_offset: 0
     }
   }
 }
@@ -56,7 +154,7 @@ enum ReadHeaderResult {
 }
 
 enum ReadBodyResult {
-  Body(Vec<u8>),
+  Body(Bytes),
   Incomplete
 }
 
@@ -66,12 +164,18 @@ impl FrameBuffer {
   }
 
   pub fn with_capacity(capacity: usize) -> FrameBuffer {
+    FrameBuffer::with_config(capacity, FrameBufferConfig::default())
+  }
+
+  pub fn with_config(capacity: usize, config: FrameBufferConfig) -> FrameBuffer {
     FrameBuffer {
-      buffer: VecDeque::with_capacity(capacity),
+      buffer: BytesMut::with_capacity(capacity),
       parse_state: ParseState::new(),
       string_pool: Pool::with_size_and_max(DEFAULT_STRING_POOL_SIZE, DEFAULT_STRING_POOL_MAX_SIZE),
       header_codec: HeaderCodec::with_pool_size_and_max(DEFAULT_HEADER_CODEC_STRING_POOL_SIZE,
-                                                        DEFAULT_HEADER_CODEC_STRING_POOL_MAX_SIZE)
+                                                        DEFAULT_HEADER_CODEC_STRING_POOL_MAX_SIZE),
+      config: config,
+      consumed_bytes: 0
     }
   }
 
@@ -79,19 +183,27 @@ impl FrameBuffer {
     self.buffer.len()
   }
 
+  /// Total number of bytes this buffer has consumed since it was created.
+  /// Used to tag `ParseError`s with the absolute offset of the failure.
+  pub fn consumed_bytes(&self) -> u64 {
+    self.consumed_bytes
+  }
+
   pub fn reset(&mut self) {
     self.buffer.clear();
     self.reset_parse_state();
   }
 
-  pub fn append(&mut self, bytes: &[u8]) {
-    for byte in bytes {
-      self.buffer.push_back(*byte);
+  pub fn append(&mut self, bytes: &[u8]) -> Result<(), ParseError> {
+    if self.buffer.len() + bytes.len() > self.config.max_frame_size {
+      return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "frame size" });
     }
+    self.buffer.extend_from_slice(bytes);
     debug!("Copied {} bytes into the frame buffer.", bytes.len());
+    Ok(())
   }
 
-  pub fn read_transmission(&mut self) -> Option<Transmission> {
+  pub fn read_transmission(&mut self) -> Result<Option<Transmission>, ParseError> {
     match self.parse_state.section {
       FrameSection::Command => self.resume_parsing_at_command(),
       FrameSection::Headers => self.resume_parsing_at_headers(),
@@ -99,42 +211,48 @@ impl FrameBuffer {
     }
   }
 
-  fn resume_parsing_at_command(&mut self) -> Option<Transmission> {
+  fn resume_parsing_at_command(&mut self) -> Result<Option<Transmission>, ParseError> {
     debug!("Parsing command.");
-    match self.read_command() {
-      ReadCommandResult::HeartBeat => Some(Transmission::HeartBeat),
+    match try!(self.read_command()) {
+      ReadCommandResult::HeartBeat => Ok(Some(Transmission::HeartBeat)),
       ReadCommandResult::Command(command_string) => {
         self.parse_state.command = Some(command_string);
         self.parse_state.section = FrameSection::Headers;
         self.resume_parsing_at_headers()
       },
-      ReadCommandResult::Incomplete => None
+      ReadCommandResult::Incomplete => Ok(None)
     }
   }
 
-  fn resume_parsing_at_headers(&mut self) -> Option<Transmission> {
+  fn resume_parsing_at_headers(&mut self) -> Result<Option<Transmission>, ParseError> {
     debug!("Parsing headers.");
     loop {
-      match self.read_header() {
+      match try!(self.read_header()) {
         ReadHeaderResult::Header(header) => {
+          if self.parse_state.headers.len() >= self.config.max_header_count {
+            return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "header count" });
+          }
           self.parse_state.headers.push(header);
         },
         ReadHeaderResult::EndOfHeaders => {
           self.parse_state.section = FrameSection::Body;
           return self.resume_parsing_at_body();
         },
-        ReadHeaderResult::Incomplete => return None
+        ReadHeaderResult::Incomplete => return Ok(None)
       }
     }
   }
 
-  fn resume_parsing_at_body(&mut self) -> Option<Transmission> {
+  fn resume_parsing_at_body(&mut self) -> Result<Option<Transmission>, ParseError> {
     debug!("Parsing body.");
-    match self.read_body() {
+    match try!(self.read_body()) {
       ReadBodyResult::Body(body_bytes) => {
         let command = match self.parse_state.command.take() {
           Some(command) => command,
-          None => panic!("No COMMAND found.")
+          None => return Err(ParseError::UnexpectedState {
+            offset: self.consumed_bytes,
+            message: "reached end of body with no COMMAND buffered"
+          })
         };
         // Consider making the HeaderList an Option<HeaderList> to allow recycling
         let headers = mem::replace(&mut self.parse_state.headers, header_list![]);
@@ -145,14 +263,15 @@ impl FrameBuffer {
           body: body
         };
         self.reset_parse_state();
-        Some(Transmission::CompleteFrame(frame))
+        Ok(Some(Transmission::CompleteFrame(frame)))
       },
-      ReadBodyResult::Incomplete => None
+      ReadBodyResult::Incomplete => Ok(None)
     }
   }
 
   fn reset_parse_state(&mut self) {
     self.parse_state.section = FrameSection::Command;
+    self.parse_state.scan_offset = 0;
   }
 
   pub fn recycle_frame(&mut self, mut frame: Frame) {
@@ -162,26 +281,29 @@ impl FrameBuffer {
     frame.headers.drain(|header| self.header_codec.recycle(header));
   }
 
-  // Replace these methods with bridge-buffer concept
-  fn read_into_vec(&mut self, n: usize) -> Vec<u8> {
-    let mut vec = Vec::with_capacity(n);
-    for _ in 0..n {
-      let byte = match self.buffer.pop_front() {
-        Some(byte) => byte,
-        None => panic!("Attempted to read beyond the end of the buffer!")
-      };
-      vec.push(byte);
+  // Splits bytes straight off the front of the buffer with no copy; the
+  // returned `Bytes` shares the underlying allocation with `self.buffer`.
+  fn read_bytes(&mut self, n: usize) -> Result<Bytes, ParseError> {
+    if self.buffer.len() < n {
+      return Err(ParseError::UnexpectedState {
+        offset: self.consumed_bytes,
+        message: "attempted to read beyond the end of the buffer"
+      });
     }
+    let bytes = self.buffer.split_to(n).freeze();
+    self.consumed_bytes += n as u64;
     debug!("Removed {} bytes from frame buffer, new size: {}", n, self.buffer.len());
-    vec
+    Ok(bytes)
   }
 
-  fn read_into_string(&mut self, n: usize) -> String {
-    let vec = self.read_into_vec(n);
-    let s = from_utf8(&vec)
-      .ok()
-      .expect("Attempted to read a string that was not utf8.");
-    self.string_pool.new_from(s).detach()
+  fn read_into_string(&mut self, n: usize) -> Result<String, ParseError> {
+    let offset_before_read = self.consumed_bytes;
+    let bytes = try!(self.read_bytes(n));
+    let s = match from_utf8(&bytes) {
+      Ok(s) => s,
+      Err(_) => return Err(ParseError::InvalidUtf8 { offset: offset_before_read })
+    };
+    Ok(self.string_pool.new_from(s).detach())
   }
 
   fn chomp(mut line: String) -> String {
@@ -196,104 +318,255 @@ impl FrameBuffer {
     line
   }
 
-  fn read_command(&mut self) -> ReadCommandResult {
+  fn read_command(&mut self) -> Result<ReadCommandResult, ParseError> {
     use frame_buffer::ReadCommandResult::*;
-    match self.find_next('\n' as u8) {
+    let scan_from = self.parse_state.scan_offset;
+    match self.find_next('\n' as u8, scan_from) {
       Some(index) => {
         debug!("Found command ending @ index {}", index);
+        self.parse_state.scan_offset = 0;
+        if index >= self.config.max_header_line_length {
+          return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "header line length" });
+        }
         let num_bytes = index + 1;
-        let command = self.read_into_string(num_bytes as usize);
+        let command = try!(self.read_into_string(num_bytes));
         let command = FrameBuffer::chomp(command);
         debug!("Chomped length: {}", command.len());
         if command == "" {
           debug!("Found HeartBeat");
           self.string_pool.attach(command);
-          return HeartBeat;
+          return Ok(HeartBeat);
         }
         if command.len() == 1 {
           debug!("Byte: {}", command.as_bytes()[0]);
         }
         debug!("Command -> '{}'", command);
-        Command(command)
+        Ok(Command(command))
       },
-      None => Incomplete
+      None => {
+        self.parse_state.scan_offset = self.buffer.len();
+        if self.buffer.len() > self.config.max_header_line_length {
+          return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "header line length" });
+        }
+        Ok(Incomplete)
+      }
     }
   }
 
-  fn read_header(&mut self) -> ReadHeaderResult {
-    match self.find_next('\n' as u8) {
+  fn read_header(&mut self) -> Result<ReadHeaderResult, ParseError> {
+    let scan_from = self.parse_state.scan_offset;
+    match self.find_next('\n' as u8, scan_from) {
       Some(index) => {
         debug!("Found header ending @ index {}", index);
+        self.parse_state.scan_offset = 0;
+        if index >= self.config.max_header_line_length {
+          return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "header line length" });
+        }
         let num_bytes = index + 1;
-        let header_string = self.read_into_string(num_bytes as usize);
+        let offset_before_header = self.consumed_bytes;
+        let header_string = try!(self.read_into_string(num_bytes));
         let header_string = FrameBuffer::chomp(header_string);
         debug!("Header -> '{}'", header_string);
         if header_string == "" {
           self.string_pool.attach(header_string);
-          return ReadHeaderResult::EndOfHeaders;
+          return Ok(ReadHeaderResult::EndOfHeaders);
         }
-        let header = self.header_codec.decode(&header_string).expect("Invalid header encountered.");
+        let header = match self.header_codec.decode(&header_string) {
+          Ok(header) => header,
+          Err(_) => return Err(ParseError::MalformedHeader { offset: offset_before_header })
+        };
         self.string_pool.attach(header_string);
-        ReadHeaderResult::Header(header)
+        Ok(ReadHeaderResult::Header(header))
       },
-      None => ReadHeaderResult::Incomplete
+      None => {
+        self.parse_state.scan_offset = self.buffer.len();
+        if self.buffer.len() > self.config.max_header_line_length {
+          return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "header line length" });
+        }
+        Ok(ReadHeaderResult::Incomplete)
+      }
     }
   }
 
-  fn read_body(&mut self) -> ReadBodyResult {
-    let maybe_body : Option<Vec<u8>> = match self.parse_state.headers.get_content_length() {
-      Some(ContentLength(num_bytes)) => self.read_body_by_content_length(num_bytes as usize),
-      None => self.read_body_by_null_octet()
+  fn read_body(&mut self) -> Result<ReadBodyResult, ParseError> {
+    let maybe_body : Option<Bytes> = match self.parse_state.headers.get_content_length() {
+      Some(ContentLength(num_bytes)) => try!(self.read_body_by_content_length(num_bytes as usize)),
+      None => try!(self.read_body_by_null_octet())
     };
     match maybe_body {
-      Some(body) => ReadBodyResult::Body(body),
-      None => ReadBodyResult::Incomplete
+      Some(body) => Ok(ReadBodyResult::Body(body)),
+      None => Ok(ReadBodyResult::Incomplete)
     }
   }
 
-  fn read_body_by_content_length(&mut self, content_length: usize) -> Option<Vec<u8>> {
+  fn read_body_by_content_length(&mut self, content_length: usize) -> Result<Option<Bytes>, ParseError> {
     debug!("Reading body by content length.");
+    if content_length > self.config.max_body_length {
+      return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "body length" });
+    }
     let bytes_needed = content_length + 1; // null octet
     if self.buffer.len() < bytes_needed {
       debug!("Not enough bytes to form body; needed {}, only had {}.", content_length, self.buffer.len());
-      return None;
+      return Ok(None);
     }
-    let mut body = self.read_into_vec(bytes_needed);
-    body.pop(); // Discard null octet
+    let mut chunk = try!(self.read_bytes(bytes_needed));
+    let body = chunk.split_to(content_length); // Remainder of `chunk` is the discarded null octet.
     debug!("Body -> '{}'", FrameBuffer::body_as_string(&body));
-    Some(body)
+    Ok(Some(body))
   }
 
-  fn read_body_by_null_octet(&mut self) -> Option<Vec<u8>> {
+  fn read_body_by_null_octet(&mut self) -> Result<Option<Bytes>, ParseError> {
     debug!("Reading body by null octet.");
-    match self.find_next(0u8) {
+    let scan_from = self.parse_state.scan_offset;
+    match self.find_next(0u8, scan_from) {
       Some(index) => {
         debug!("Found body ending @ index {}", index);
+        self.parse_state.scan_offset = 0;
+        if index > self.config.max_body_length {
+          return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "body length" });
+        }
         let num_bytes = index + 1;
-        let mut body = self.read_into_vec(num_bytes as usize);
-        body.pop(); // Discard null octet
+        let mut chunk = try!(self.read_bytes(num_bytes));
+        let body = chunk.split_to(index); // Remainder of `chunk` is the discarded null octet.
         debug!("Body -> '{}'", FrameBuffer::body_as_string(&body));
-        Some(body)
+        Ok(Some(body))
       },
-      None => None
+      None => {
+        self.parse_state.scan_offset = self.buffer.len();
+        if self.buffer.len() > self.config.max_body_length {
+          return Err(ParseError::LimitExceeded { offset: self.consumed_bytes, limit: "body length" });
+        }
+        Ok(None)
+      }
     }
   }
 
-  fn body_as_string(body: &Vec<u8>) -> &str {
-    match from_utf8(&body) {
-      Ok(ref s) => *s,
+  fn body_as_string(body: &Bytes) -> &str {
+    match from_utf8(body) {
+      Ok(s) => s,
       Err(_) => "<Non-utf8 Binary Content>"
     }
   }
 
-  fn find_next(&self, needle: u8) -> Option<u32> {
-    let mut step = 0u32;
-    for byte in &self.buffer {
-      if *byte == needle {
-        return Some(step);
-      }
-      step += 1;
+  // Scans for `needle` starting at byte `start`, using memchr's SIMD search
+  // instead of a byte-at-a-time loop. Callers that re-scan a partially
+  // arrived line pass their prior `scan_offset` so a frame trickling in over
+  // N chunks is scanned in O(total bytes) rather than O(N * bytes).
+  fn find_next(&self, needle: u8, start: usize) -> Option<usize> {
+    if start >= self.buffer.len() {
+      return None;
+    }
+    memchr(needle, &self.buffer[start..]).map(|index| index + start)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config(max_frame_size: usize,
+            max_header_line_length: usize,
+            max_header_count: usize,
+            max_body_length: usize) -> FrameBufferConfig {
+    FrameBufferConfig::new(max_frame_size, max_header_line_length, max_header_count, max_body_length)
+  }
+
+  #[test]
+  fn append_rejects_frames_over_the_max_frame_size() {
+    let mut buffer = FrameBuffer::with_config(16, config(5, 1024, 100, 1024));
+    match buffer.append(b"0123456789") {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "frame size"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn unterminated_command_line_over_the_limit_is_rejected() {
+    let mut buffer = FrameBuffer::with_config(64, config(1024, 10, 100, 1024));
+    buffer.append(b"a-command-line-with-no-terminator").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "header line length"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn too_many_headers_is_rejected() {
+    let mut buffer = FrameBuffer::with_config(64, config(1024, 1024, 1, 1024));
+    buffer.append(b"CONNECT\na:1\nb:2\n\n\0").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "header count"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn declared_content_length_over_the_limit_is_rejected_before_buffering() {
+    let mut buffer = FrameBuffer::with_config(64, config(1024, 1024, 100, 10));
+    buffer.append(b"CONNECT\ncontent-length:20\n\n").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "body length"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn unterminated_null_octet_body_over_the_limit_is_rejected() {
+    let mut buffer = FrameBuffer::with_config(64, config(1024, 1024, 100, 5));
+    buffer.append(b"CONNECT\n\n123456").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "body length"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn complete_null_octet_body_over_the_limit_is_rejected() {
+    let mut buffer = FrameBuffer::with_config(64, config(1024, 1024, 100, 5));
+    buffer.append(b"CONNECT\n\n123456\0").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "body length"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn complete_command_line_over_the_limit_is_rejected() {
+    let mut buffer = FrameBuffer::with_config(1024, config(1024, 10, 100, 1024));
+    buffer.append(b"a-command-line-that-is-too-long\n").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "header line length"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn complete_header_line_over_the_limit_is_rejected() {
+    let mut buffer = FrameBuffer::with_config(1024, config(1024, 10, 100, 1024));
+    buffer.append(b"CONNECT\na-header-line-that-is-too-long:1\n").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, "header line length"),
+      other => panic!("expected LimitExceeded, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn invalid_utf8_command_reports_the_offset_it_was_found_at() {
+    let mut buffer = FrameBuffer::with_config(64, FrameBufferConfig::default());
+    buffer.append(&[0xff, 0xfe, b'\n']).unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::InvalidUtf8 { offset }) => assert_eq!(offset, 0),
+      other => panic!("expected InvalidUtf8, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn malformed_header_reports_the_offset_it_was_found_at() {
+    let mut buffer = FrameBuffer::with_config(64, FrameBufferConfig::default());
+    buffer.append(b"CONNECT\nnot-a-valid-header\n\n\0").unwrap();
+    match buffer.read_transmission() {
+      Err(ParseError::MalformedHeader { offset }) => assert_eq!(offset, 8),
+      other => panic!("expected MalformedHeader, got {:?}", other)
     }
-    None
   }
 }
@@ -0,0 +1,122 @@
+use std::io::Read;
+use frame::Transmission;
+use frame_buffer::{FrameBuffer, FrameBufferConfig, ParseError};
+
+const DEFAULT_REFILL_SIZE: usize = 4 * 1024;
+
+/// Drives a `FrameBuffer` to completion against a `Read`, so callers no
+/// longer have to hand-roll the "append bytes, drain transmissions, refill"
+/// loop themselves:
+///
+/// ```no_run
+/// # use std::net::TcpStream;
+/// let tcp_stream = TcpStream::connect("localhost:61613").unwrap();
+/// for frame in FrameReader::new(tcp_stream) {
+///   match frame {
+///     Ok(transmission) => { /* ... */ },
+///     Err(parse_error) => { /* ... */ }
+///   }
+/// }
+/// ```
+pub struct FrameReader<R: Read> {
+  reader: R,
+  frame_buffer: FrameBuffer,
+  refill_size: usize,
+  eof: bool,
+  done: bool
+}
+
+impl<R: Read> FrameReader<R> {
+  pub fn new(reader: R) -> FrameReader<R> {
+    FrameReader::with_config(reader, FrameBufferConfig::default(), DEFAULT_REFILL_SIZE)
+  }
+
+  pub fn with_config(reader: R, config: FrameBufferConfig, refill_size: usize) -> FrameReader<R> {
+    FrameReader {
+      reader: reader,
+      frame_buffer: FrameBuffer::with_config(1024 * 64, config),
+      refill_size: refill_size,
+      eof: false,
+      done: false
+    }
+  }
+
+  // Reads up to `refill_size` bytes from the underlying reader into the
+  // frame buffer. Returns the number of bytes read, with 0 meaning EOF.
+  fn refill(&mut self) -> Result<usize, ParseError> {
+    let mut chunk = vec![0u8; self.refill_size];
+    let bytes_read = match self.reader.read(&mut chunk) {
+      Ok(bytes_read) => bytes_read,
+      Err(io_error) => return Err(ParseError::Io {
+        offset: self.frame_buffer.consumed_bytes(),
+        message: io_error.to_string()
+      })
+    };
+    if bytes_read > 0 {
+      try!(self.frame_buffer.append(&chunk[..bytes_read]));
+    }
+    Ok(bytes_read)
+  }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+  type Item = Result<Transmission, ParseError>;
+
+  fn next(&mut self) -> Option<Result<Transmission, ParseError>> {
+    if self.done {
+      return None;
+    }
+    loop {
+      match self.frame_buffer.read_transmission() {
+        Ok(Some(transmission)) => return Some(Ok(transmission)),
+        Err(parse_error) => {
+          self.done = true;
+          return Some(Err(parse_error));
+        },
+        Ok(None) => {
+          if self.eof {
+            self.done = true;
+            if self.frame_buffer.len() > 0 {
+              return Some(Err(ParseError::UnexpectedState {
+                offset: self.frame_buffer.consumed_bytes(),
+                message: "reached end of stream with a partially-buffered frame"
+              }));
+            }
+            return None;
+          }
+          match self.refill() {
+            Ok(0) => self.eof = true,
+            Ok(_) => { /* Loop back around and try to parse again. */ },
+            Err(parse_error) => {
+              self.done = true;
+              return Some(Err(parse_error));
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  #[test]
+  fn partial_frame_at_eof_yields_a_terminal_error_then_ends() {
+    let mut reader = FrameReader::new(Cursor::new(b"CONNECT".to_vec()));
+    match reader.next() {
+      Some(Err(ParseError::UnexpectedState { message, .. })) =>
+        assert_eq!(message, "reached end of stream with a partially-buffered frame"),
+      other => panic!("expected a terminal UnexpectedState error, got {:?}", other)
+    }
+    assert!(reader.next().is_none());
+  }
+
+  #[test]
+  fn clean_eof_with_no_partial_frame_just_ends() {
+    let mut reader = FrameReader::new(Cursor::new(Vec::new()));
+    assert!(reader.next().is_none());
+  }
+}